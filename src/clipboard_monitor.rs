@@ -0,0 +1,45 @@
+use std::os::unix::io::RawFd;
+
+use thiserror::Error;
+
+use crate::wayland_clipboard_monitor::WaylandClipboardMonitorError;
+use crate::x11_clipboard_monitor::{SelectionKind, X11ClipboardMonitorError};
+
+/// Error returned by the unified `ClipboardMonitor` trait. Wraps whichever backend
+/// (X11 or Wayland) is actually running, so callers going through `new_clipboard_monitor`
+/// can match on the specific failure instead of downcasting a `Box<dyn Error>`.
+#[derive(Error, Debug)]
+pub enum ClipboardMonitorError {
+	#[error(transparent)]
+	X11(#[from] X11ClipboardMonitorError),
+	#[error(transparent)]
+	Wayland(#[from] WaylandClipboardMonitorError),
+	#[error("the Wayland backend only supports the CLIPBOARD selection, not {0:?}")]
+	UnsupportedSelectionKind(SelectionKind),
+}
+
+/// A clipboard backend, implemented separately for each display server
+/// (X11 via `X11ClipboardMonitor`, Wayland via `WaylandClipboardMonitor`). Lets callers
+/// and the runtime-selecting constructor in the crate root work against either session
+/// type without caring which one is actually running.
+pub trait ClipboardMonitor {
+	/// Blocks until the clipboard changes, then returns its text contents.
+	fn next_clipboard_string(&self) -> Result<String, ClipboardMonitorError>;
+
+	/// Non-blocking counterpart to `next_clipboard_string`: returns `Ok(None)` immediately
+	/// if no clipboard change is queued yet. Pair with `file_descriptor` to wait for
+	/// readability in an external reactor instead of blocking the calling thread.
+	fn poll_clipboard_string(&self) -> Result<Option<String>, ClipboardMonitorError>;
+
+	/// Drains every clipboard change currently queued, oldest first.
+	fn drain_clipboard_strings(&self) -> Result<Vec<String>, ClipboardMonitorError>;
+
+	/// Becomes the owner of the clipboard, offering `data` to requestors, and blocks
+	/// until ownership is lost (another client claims the clipboard).
+	fn set_clipboard_string(&self, data: String) -> Result<(), ClipboardMonitorError>;
+
+	/// The underlying connection's file descriptor. Readable when an event (e.g. a
+	/// clipboard change) is pending, so it can be registered with `mio`/`epoll` alongside
+	/// other I/O instead of dedicating a thread to `next_clipboard_string`.
+	fn file_descriptor(&self) -> RawFd;
+}
@@ -0,0 +1,33 @@
+mod clipboard_monitor;
+mod wayland_clipboard_monitor;
+mod x11_clipboard_monitor;
+
+pub use clipboard_monitor::{ClipboardMonitor, ClipboardMonitorError};
+pub use wayland_clipboard_monitor::{WaylandClipboardMonitor, WaylandClipboardMonitorError};
+pub use x11_clipboard_monitor::{SelectionKind, X11ClipboardMonitor, X11ClipboardMonitorError};
+
+#[cfg(feature = "image-data")]
+pub use x11_clipboard_monitor::ImageData;
+
+/// Picks the right backend for the current session: Wayland's `wlr-data-control` if
+/// `WAYLAND_DISPLAY` is set, X11 otherwise. Downstream code that only needs the
+/// `ClipboardMonitor` trait can call this instead of choosing a backend itself. Monitors
+/// the `CLIPBOARD` selection; use `new_clipboard_monitor_for` to pick PRIMARY/SECONDARY.
+pub fn new_clipboard_monitor() -> Result<Box<dyn ClipboardMonitor>, ClipboardMonitorError> {
+	new_clipboard_monitor_for(SelectionKind::Clipboard)
+}
+
+/// Like `new_clipboard_monitor`, but monitors the given selection instead of always
+/// using `CLIPBOARD`. The Wayland backend's `wlr-data-control` protocol only covers the
+/// clipboard selection, so `kind` other than `Clipboard` only works on X11.
+pub fn new_clipboard_monitor_for(kind: SelectionKind) -> Result<Box<dyn ClipboardMonitor>, ClipboardMonitorError> {
+	if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+		if kind != SelectionKind::Clipboard {
+			return Err(ClipboardMonitorError::UnsupportedSelectionKind(kind));
+		}
+
+		Ok(Box::new(WaylandClipboardMonitor::new()?))
+	} else {
+		Ok(Box::new(X11ClipboardMonitor::new_for(kind)?))
+	}
+}
@@ -0,0 +1,335 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::string::FromUtf8Error;
+
+use thiserror::Error;
+
+use wayland_client::backend::WaylandError;
+use wayland_client::globals::{registry_queue_init, BindError, GlobalError, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{event_created_child, Connection, ConnectError, Dispatch, DispatchError, EventQueue, Proxy, QueueHandle};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1;
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1};
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1};
+
+use crate::{ClipboardMonitor, ClipboardMonitorError};
+
+// the MIME types we offer/accept for plain text, most-preferred first
+const TEXT_MIME_TYPES: [&str; 3] = ["text/plain;charset=utf-8", "UTF8_STRING", "text/plain"];
+
+#[derive(Error, Debug)]
+pub enum WaylandClipboardMonitorError {
+	#[error("failed to connect to the Wayland compositor")]
+	ConnectionFailed(#[from] ConnectError),
+	#[error("failed to query the Wayland registry")]
+	RegistryFailed(#[from] GlobalError),
+	#[error("failed to bind a required Wayland global")]
+	BindFailed(#[from] BindError),
+	#[error("the connection to the Wayland compositor was lost")]
+	Dispatch(#[from] DispatchError),
+	#[error("the connection to the Wayland compositor was lost")]
+	Connection(#[from] WaylandError),
+	#[error(transparent)]
+	Io(#[from] std::io::Error),
+	#[error("clipboard data was not valid UTF-8")]
+	InvalidUtf8(#[from] FromUtf8Error),
+	#[error("the selection offer has no text mime type")]
+	NoTextMimeType,
+}
+
+#[derive(Default)]
+struct AppState {
+	mime_types: HashMap<u32, Vec<String>>,
+	selection: Option<ZwlrDataControlOfferV1>,
+	// the data we're currently offering while we own the selection, kept alive until
+	// the compositor cancels our source
+	owned_data: Option<String>,
+	owned_source: Option<ZwlrDataControlSourceV1>,
+}
+
+/// Clipboard backend for Wayland compositors that implement the `wlr-data-control`
+/// protocol (e.g. sway, Hyprland). See `X11ClipboardMonitor` for the X11 equivalent.
+pub struct WaylandClipboardMonitor {
+	event_queue: RefCell<EventQueue<AppState>>,
+	qh: QueueHandle<AppState>,
+	state: RefCell<AppState>,
+	manager: ZwlrDataControlManagerV1,
+	device: ZwlrDataControlDeviceV1,
+}
+
+impl WaylandClipboardMonitor {
+	pub fn new() -> Result<Self, WaylandClipboardMonitorError> {
+		let connection = Connection::connect_to_env()?;
+		let (globals, mut event_queue) = registry_queue_init::<AppState>(&connection)?;
+		let qh = event_queue.handle();
+
+		// `registry_queue_init` only records the compositor's initial globals into
+		// `GlobalListContents`; it doesn't forward them to our `Dispatch<WlRegistry, _>`
+		// impl, so the seat has to be bound from the `GlobalList` itself, the same way
+		// the manager is below, rather than waited for as a `Global` event
+		let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=7, ())?;
+		let manager: ZwlrDataControlManagerV1 = globals.bind(&qh, 1..=2, ())?;
+		let device = manager.get_data_device(&seat, &qh, ());
+
+		let mut state = AppState::default();
+		event_queue.roundtrip(&mut state)?;
+
+		// the compositor announces the current selection as soon as the device is created;
+		// drop that offer so only later changes are reported, as with the X11 backend
+		if let Some(offer) = state.selection.take() {
+			state.mime_types.remove(&offer.id().protocol_id());
+			offer.destroy();
+		}
+
+		Ok(Self { event_queue: RefCell::new(event_queue), qh, state: RefCell::new(state), manager, device })
+	}
+
+	fn receive_string(
+		event_queue: &mut EventQueue<AppState>,
+		state: &mut AppState,
+		offer: ZwlrDataControlOfferV1,
+	) -> Result<String, WaylandClipboardMonitorError> {
+		// walk TEXT_MIME_TYPES in preference order and pick the first one the offer
+		// actually has, rather than the offer's own (unordered) announcement order
+		let mimes = state.mime_types.remove(&offer.id().protocol_id()).unwrap_or_default();
+		let mime_type = TEXT_MIME_TYPES.into_iter().find(|mime| mimes.iter().any(|m| m == mime));
+
+		let mime_type = match mime_type {
+			Some(mime_type) => mime_type,
+			None => {
+				offer.destroy();
+				return Err(WaylandClipboardMonitorError::NoTextMimeType);
+			}
+		};
+
+		let (mut reader, writer) = std::io::pipe()?;
+		offer.receive(mime_type.to_string(), writer.as_fd());
+		drop(writer);
+
+		event_queue.flush()?;
+
+		let mut buffer = Vec::new();
+		reader.read_to_end(&mut buffer)?;
+
+		event_queue.roundtrip(state)?;
+
+		offer.destroy();
+
+		Ok(String::from_utf8(buffer)?)
+	}
+
+	pub fn next_clipboard_string(&self) -> Result<String, WaylandClipboardMonitorError> {
+		let mut event_queue = self.event_queue.borrow_mut();
+		let mut state = self.state.borrow_mut();
+
+		let offer = loop {
+			if let Some(offer) = state.selection.take() {
+				break offer;
+			}
+
+			event_queue.blocking_dispatch(&mut state)?;
+		};
+
+		Self::receive_string(&mut event_queue, &mut state, offer)
+	}
+
+	/// Non-blocking counterpart to `next_clipboard_string`: returns `Ok(None)` immediately
+	/// if no clipboard change is queued yet. Pair with `file_descriptor` to wait for
+	/// readability in an external reactor instead of blocking the calling thread.
+	pub fn poll_clipboard_string(&self) -> Result<Option<String>, WaylandClipboardMonitorError> {
+		let mut event_queue = self.event_queue.borrow_mut();
+		let mut state = self.state.borrow_mut();
+
+		event_queue.dispatch_pending(&mut state)?;
+
+		if fd_readable(event_queue.as_fd())? {
+			event_queue.flush()?;
+
+			if let Some(guard) = event_queue.prepare_read() {
+				guard.read()?;
+			}
+
+			event_queue.dispatch_pending(&mut state)?;
+		}
+
+		match state.selection.take() {
+			Some(offer) => Ok(Some(Self::receive_string(&mut event_queue, &mut state, offer)?)),
+			None => Ok(None),
+		}
+	}
+
+	/// Drains every clipboard change currently queued, oldest first.
+	pub fn drain_clipboard_strings(&self) -> Result<Vec<String>, WaylandClipboardMonitorError> {
+		let mut strings = Vec::new();
+
+		while let Some(string) = self.poll_clipboard_string()? {
+			strings.push(string);
+		}
+
+		Ok(strings)
+	}
+
+	/// Becomes the owner of the clipboard, offering `data` to requestors, and blocks
+	/// until ownership is lost (another client claims the clipboard).
+	pub fn set_clipboard_string(&self, data: String) -> Result<(), WaylandClipboardMonitorError> {
+		let mut event_queue = self.event_queue.borrow_mut();
+		let mut state = self.state.borrow_mut();
+
+		let source = self.manager.create_data_source(&self.qh, ());
+
+		for mime_type in TEXT_MIME_TYPES {
+			source.offer(mime_type.to_string());
+		}
+
+		self.device.set_selection(Some(&source));
+
+		state.owned_data = Some(data);
+		state.owned_source = Some(source);
+
+		event_queue.flush()?;
+
+		while state.owned_source.is_some() {
+			event_queue.blocking_dispatch(&mut state)?;
+		}
+
+		Ok(())
+	}
+
+	/// The underlying connection's file descriptor. Readable when an event (e.g. a
+	/// clipboard change) is pending, so it can be registered with `mio`/`epoll` alongside
+	/// other I/O instead of dedicating a thread to `next_clipboard_string`.
+	pub fn file_descriptor(&self) -> RawFd {
+		self.event_queue.borrow().as_fd().as_raw_fd()
+	}
+}
+
+impl ClipboardMonitor for WaylandClipboardMonitor {
+	fn next_clipboard_string(&self) -> Result<String, ClipboardMonitorError> {
+		Ok(WaylandClipboardMonitor::next_clipboard_string(self)?)
+	}
+
+	fn poll_clipboard_string(&self) -> Result<Option<String>, ClipboardMonitorError> {
+		Ok(WaylandClipboardMonitor::poll_clipboard_string(self)?)
+	}
+
+	fn drain_clipboard_strings(&self) -> Result<Vec<String>, ClipboardMonitorError> {
+		Ok(WaylandClipboardMonitor::drain_clipboard_strings(self)?)
+	}
+
+	fn set_clipboard_string(&self, data: String) -> Result<(), ClipboardMonitorError> {
+		Ok(WaylandClipboardMonitor::set_clipboard_string(self, data)?)
+	}
+
+	fn file_descriptor(&self) -> RawFd {
+		WaylandClipboardMonitor::file_descriptor(self)
+	}
+}
+
+fn fd_readable(fd: BorrowedFd) -> Result<bool, std::io::Error> {
+	let mut poll_fd = libc::pollfd { fd: fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+
+	match unsafe { libc::poll(&mut poll_fd, 1, 0) } {
+		ret if ret < 0 => Err(std::io::Error::last_os_error()),
+		ret => Ok(ret > 0 && poll_fd.revents & libc::POLLIN != 0),
+	}
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for AppState {
+	// globals are bound up front from the `GlobalList` returned by `registry_queue_init`
+	// (see `WaylandClipboardMonitor::new`); we don't react to globals that come and go
+	// afterward, so there's nothing to do here
+	fn event(
+		_state: &mut Self,
+		_proxy: &wl_registry::WlRegistry,
+		_event: wl_registry::Event,
+		_data: &GlobalListContents,
+		_conn: &Connection,
+		_qh: &QueueHandle<Self>,
+	) {
+	}
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for AppState {
+	fn event(_state: &mut Self, _proxy: &wl_seat::WlSeat, _event: wl_seat::Event, _data: &(), _conn: &Connection, _qh: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for AppState {
+	fn event(
+		_state: &mut Self,
+		_proxy: &ZwlrDataControlManagerV1,
+		_event: wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_manager_v1::Event,
+		_data: &(),
+		_conn: &Connection,
+		_qh: &QueueHandle<Self>,
+	) {
+	}
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for AppState {
+	fn event(
+		state: &mut Self,
+		_proxy: &ZwlrDataControlDeviceV1,
+		event: zwlr_data_control_device_v1::Event,
+		_data: &(),
+		_conn: &Connection,
+		_qh: &QueueHandle<Self>,
+	) {
+		if let zwlr_data_control_device_v1::Event::Selection { id } = event {
+			// per the protocol, a wlr_data_control_offer is valid until a new one (or
+			// NULL) is received, and we must destroy the previous one upon receiving it
+			if let Some(offer) = std::mem::replace(&mut state.selection, id) {
+				offer.destroy();
+			}
+		}
+	}
+
+	// `data_offer` is the only event on this interface carrying a `new_id` (the
+	// `ZwlrDataControlOfferV1` that `selection` and `primary_selection` subsequently
+	// reference by existing object id, not a fresh one); without this, wayland-client's
+	// default impl panics the first time the compositor offers a selection
+	event_created_child!(AppState, ZwlrDataControlDeviceV1, [
+		zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+	]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for AppState {
+	fn event(
+		state: &mut Self,
+		proxy: &ZwlrDataControlOfferV1,
+		event: zwlr_data_control_offer_v1::Event,
+		_data: &(),
+		_conn: &Connection,
+		_qh: &QueueHandle<Self>,
+	) {
+		if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+			state.mime_types.entry(proxy.id().protocol_id()).or_default().push(mime_type);
+		}
+	}
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, ()> for AppState {
+	fn event(
+		state: &mut Self,
+		_proxy: &ZwlrDataControlSourceV1,
+		event: zwlr_data_control_source_v1::Event,
+		_data: &(),
+		_conn: &Connection,
+		_qh: &QueueHandle<Self>,
+	) {
+		match event {
+			zwlr_data_control_source_v1::Event::Send { fd, .. } => {
+				if let Some(data) = &state.owned_data {
+					let _ = std::fs::File::from(fd).write_all(data.as_bytes());
+				}
+			}
+			zwlr_data_control_source_v1::Event::Cancelled => {
+				state.owned_data = None;
+				state.owned_source = None;
+			}
+			_ => (),
+		}
+	}
+}
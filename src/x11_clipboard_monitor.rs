@@ -1,43 +1,126 @@
+use std::cell::{Cell, RefCell};
+use std::os::unix::io::{AsRawFd, RawFd};
 use std::str;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "image-data")]
+use std::borrow::Cow;
 
 use thiserror::Error;
 
-use x11rb::connection::Connection;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::errors::{ConnectError, ConnectionError, ReplyError, ReplyOrIdError};
 use x11rb::protocol::xproto::*;
 use x11rb::COPY_DEPTH_FROM_PARENT;
 use x11rb::protocol::Event;
-use x11rb::protocol::xfixes::ConnectionExt as _;
+use x11rb::protocol::xfixes::{
+	ConnectionExt as _, SelectionEventMask, SelectionNotifyEvent as XfixesSelectionNotifyEvent,
+};
 use x11rb::rust_connection::RustConnection;
 
+use crate::{ClipboardMonitor, ClipboardMonitorError};
+
 // inspired by https://www.uninformativ.de/blog/postings/2017-04-02/0/POSTING-en.html and https://docs.rs/x11-clipboard/0.5.3/src/x11_clipboard/lib.rs.html
 
 #[derive(Error, Debug)]
-enum X11ClipboardMonitorError {
+pub enum X11ClipboardMonitorError {
+	#[error("failed to connect to the X server")]
+	ConnectionFailed(#[from] ConnectError),
+	#[error("the connection to the X server was lost")]
+	Connection(#[from] ConnectionError),
+	#[error("the X server returned an error")]
+	Reply(#[from] ReplyError),
+	#[error("failed to allocate an X resource")]
+	ReplyOrId(#[from] ReplyOrIdError),
+	#[error("the display has no screen numbered {0}")]
+	NoScreen(usize),
+	#[error("clipboard data was not valid UTF-8")]
+	InvalidUtf8(#[from] str::Utf8Error),
+	#[cfg(feature = "image-data")]
+	#[error("failed to decode clipboard image data")]
+	ImageDecodeFailed(#[from] image::ImageError),
 	#[error("clipboard conversion has failed")]
 	ConversionFailed,
-	#[error("incr x extension is unsupported")]
-	IncrUnsupported,
 	#[error("the selection has lost it's owner")]
-	SelectionOrphaned
+	SelectionOrphaned,
+	#[error("timed out waiting for an INCR transfer to complete")]
+	IncrTimeout,
+	#[error("timed out waiting for the requestor to consume an INCR chunk")]
+	IncrSendTimeout,
+	#[error("failed to become the selection owner")]
+	OwnershipFailed
+}
+
+const INCR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The X11 selection to monitor. `Clipboard` is what applications use for explicit
+/// copy/paste; `Primary` tracks the current text selection (middle-click paste).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionKind {
+	Clipboard,
+	Primary,
+	Secondary
+}
+
+impl SelectionKind {
+	fn atom_name(&self) -> &'static [u8] {
+		match self {
+			SelectionKind::Clipboard => b"CLIPBOARD",
+			SelectionKind::Primary => b"PRIMARY",
+			SelectionKind::Secondary => b"SECONDARY"
+		}
+	}
+}
+
+// outcome of answering one SelectionRequest target: whether we served it directly,
+// declined it, or announced an INCR transfer whose chunks still need to be sent
+enum SelectionReply {
+	Served,
+	NotServed,
+	Incr(Vec<u8>),
 }
 
 pub struct X11ClipboardMonitor {
 	connection: RustConnection,
 	receiver_window: Window,
-	atoms: Atoms
+	atoms: Atoms,
+	// the data we're currently offering while we own the selection, and the timestamp
+	// we claimed it at (needed to answer TIMESTAMP requests)
+	owned_selection: RefCell<Option<Vec<u8>>>,
+	owned_since: Cell<Timestamp>,
 }
 
 struct Atoms {
-	clipboard: Atom,
+	selection: Atom,
 	utf8_string: Atom,
+	string: Atom,
+	text_plain_utf8: Atom,
+	text_plain: Atom,
+	targets: Atom,
+	timestamp: Atom,
 	receiver_property: Atom,
 	incr: Atom,
+	#[cfg(feature = "image-data")]
+	image_png: Atom,
+}
+
+/// Decoded clipboard image data: raw, row-major RGBA pixels.
+#[cfg(feature = "image-data")]
+pub struct ImageData<'a> {
+	pub width: u32,
+	pub height: u32,
+	pub bytes: Cow<'a, [u8]>
 }
 
 impl X11ClipboardMonitor {
-	pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-		let (connection, screen_num) = x11rb::connect(None).unwrap();
-		let screen = &connection.setup().roots[screen_num];
+	pub fn new() -> Result<Self, X11ClipboardMonitorError> {
+		Self::new_for(SelectionKind::Clipboard)
+	}
+
+	pub fn new_for(kind: SelectionKind) -> Result<Self, X11ClipboardMonitorError> {
+		let (connection, screen_num) = x11rb::connect(None)?;
+		let screen = connection.setup().roots.get(screen_num).ok_or(X11ClipboardMonitorError::NoScreen(screen_num))?;
 		let receiver_window = connection.generate_id()?;
 
 		connection.create_window(
@@ -55,63 +138,354 @@ impl X11ClipboardMonitor {
 		)?;
 
 		let atoms = Atoms {
-			clipboard: connection.intern_atom(false, b"CLIPBOARD")?.reply()?.atom,
+			selection: connection.intern_atom(false, kind.atom_name())?.reply()?.atom,
 			utf8_string: connection.intern_atom(false, b"UTF8_STRING")?.reply()?.atom,
+			string: connection.intern_atom(false, b"STRING")?.reply()?.atom,
+			text_plain_utf8: connection.intern_atom(false, b"text/plain;charset=utf-8")?.reply()?.atom,
+			text_plain: connection.intern_atom(false, b"text/plain")?.reply()?.atom,
+			targets: connection.intern_atom(false, b"TARGETS")?.reply()?.atom,
+			timestamp: connection.intern_atom(false, b"TIMESTAMP")?.reply()?.atom,
 			receiver_property: connection.intern_atom(false, b"CLIPBOARD_RECEIVER")?.reply()?.atom,
 			incr: connection.intern_atom(false, b"INCR")?.reply()?.atom,
+			#[cfg(feature = "image-data")]
+			image_png: connection.intern_atom(false, b"image/png")?.reply()?.atom,
 		};
 
 		connection.xfixes_query_version(100, 0)?.reply()?;
 
-		connection.xfixes_select_selection_input(screen.root, atoms.clipboard, 1_u8)?.check()?;
+		connection
+			.xfixes_select_selection_input(screen.root, atoms.selection, SelectionEventMask::SET_SELECTION_OWNER)?
+			.check()?;
 
 		connection.flush()?;
 
 		Ok(Self {
 			connection,
 			receiver_window,
-			atoms
+			atoms,
+			owned_selection: RefCell::new(None),
+			owned_since: Cell::new(x11rb::CURRENT_TIME)
 		})
 	}
 
-	pub fn next_clipboard_string(&self) -> Result<String, Box<dyn std::error::Error>> {
-		let clipboard_changed_event;
+	pub fn next_clipboard_string(&self) -> Result<String, X11ClipboardMonitorError> {
+		let clipboard_changed_event = self.wait_for_selection_change()?;
+		self.clipboard_string_for(clipboard_changed_event)
+	}
+
+	/// Non-blocking counterpart to `next_clipboard_string`: returns `Ok(None)` immediately
+	/// if no clipboard change is queued yet. Pair with `file_descriptor` to wait for
+	/// readability in an external reactor instead of blocking the calling thread.
+	pub fn poll_clipboard_string(&self) -> Result<Option<String>, X11ClipboardMonitorError> {
+		self.poll_for_selection_change()?
+			.map(|event| self.clipboard_string_for(event))
+			.transpose()
+	}
+
+	/// Drains every clipboard change currently queued on the connection, oldest first.
+	pub fn drain_clipboard_strings(&self) -> Result<Vec<String>, X11ClipboardMonitorError> {
+		let mut strings = Vec::new();
+
+		while let Some(string) = self.poll_clipboard_string()? {
+			strings.push(string);
+		}
+
+		Ok(strings)
+	}
+
+	/// Decodes the clipboard's `image/png` target into raw RGBA pixels, blocking until
+	/// the next clipboard change. Requires the `image-data` feature.
+	#[cfg(feature = "image-data")]
+	pub fn next_clipboard_image(&self) -> Result<ImageData<'static>, X11ClipboardMonitorError> {
+		let clipboard_changed_event = self.wait_for_selection_change()?;
+		self.clipboard_image_for(clipboard_changed_event)
+	}
 
+	/// Non-blocking counterpart to `next_clipboard_image`. Requires the `image-data` feature.
+	#[cfg(feature = "image-data")]
+	pub fn poll_clipboard_image(&self) -> Result<Option<ImageData<'static>>, X11ClipboardMonitorError> {
+		self.poll_for_selection_change()?
+			.map(|event| self.clipboard_image_for(event))
+			.transpose()
+	}
+
+	/// The underlying connection's file descriptor. Readable when an event (e.g. a
+	/// clipboard change) is pending, so it can be registered with `mio`/`epoll` alongside
+	/// other I/O instead of dedicating a thread to `next_clipboard_string`.
+	pub fn file_descriptor(&self) -> RawFd {
+		self.connection.stream().as_raw_fd()
+	}
+
+	fn clipboard_string_for(&self, event: XfixesSelectionNotifyEvent) -> Result<String, X11ClipboardMonitorError> {
+		let target = self.preferred_string_target()?;
+		let bytes = self.convert_and_fetch(event.selection, target, event.timestamp)?;
+
+		Ok(str::from_utf8(&bytes)?.into())
+	}
+
+	#[cfg(feature = "image-data")]
+	fn clipboard_image_for(&self, event: XfixesSelectionNotifyEvent) -> Result<ImageData<'static>, X11ClipboardMonitorError> {
+		let bytes = self.convert_and_fetch(event.selection, self.atoms.image_png, event.timestamp)?;
+
+		let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)?.into_rgba8();
+		let (width, height) = image.dimensions();
+
+		Ok(ImageData { width, height, bytes: Cow::Owned(image.into_raw()) })
+	}
+
+	/// Becomes the owner of the selection, offering `data` to requestors, and blocks
+	/// serving `SelectionRequest`s until ownership is lost (another client claims the
+	/// selection, e.g. by calling this again from elsewhere, or writing to it directly).
+	pub fn set_clipboard_string(&self, data: String) -> Result<(), X11ClipboardMonitorError> {
+		let timestamp = self.current_timestamp()?;
+
+		*self.owned_selection.borrow_mut() = Some(data.into_bytes());
+		self.owned_since.set(timestamp);
+
+		self.connection.set_selection_owner(self.receiver_window, self.atoms.selection, timestamp)?.check()?;
+
+		let owner = self.connection.get_selection_owner(self.atoms.selection)?.reply()?.owner;
+
+		if owner != self.receiver_window {
+			*self.owned_selection.borrow_mut() = None;
+			return Err(X11ClipboardMonitorError::OwnershipFailed);
+		}
+
+		self.connection.flush()?;
+
+		self.serve_selection_requests()
+	}
+
+	fn serve_selection_requests(&self) -> Result<(), X11ClipboardMonitorError> {
 		loop {
 			match self.connection.wait_for_event()? {
-				Event::XfixesSelectionNotify(event) => {
-					clipboard_changed_event = event;
-					break
+				Event::SelectionRequest(event) => self.handle_selection_request(event)?,
+				Event::SelectionClear(_) => {
+					*self.owned_selection.borrow_mut() = None;
+					return Ok(())
 				},
 				_ => (),
 			};
 		}
+	}
+
+	fn handle_selection_request(&self, request: SelectionRequestEvent) -> Result<(), X11ClipboardMonitorError> {
+		let property = if request.property == AtomEnum::NONE.into() { request.target } else { request.property };
+
+		let reply = if request.target == self.atoms.targets {
+			self.reply_targets(request.requestor, property)?;
+			SelectionReply::Served
+		} else if request.target == self.atoms.timestamp {
+			self.reply_timestamp(request.requestor, property)?;
+			SelectionReply::Served
+		} else if request.target == self.atoms.utf8_string || request.target == self.atoms.string {
+			self.reply_string(request.requestor, property, request.target)?
+		} else {
+			SelectionReply::NotServed
+		};
+
+		// the requestor must only delete the INCR property (to ask for the first/next
+		// chunk) once it has seen this SelectionNotify, so we have to send it before
+		// entering send_incr_chunks's wait loop below
+		self.connection.send_event(
+			false,
+			request.requestor,
+			EventMask::NO_EVENT,
+			SelectionNotifyEvent {
+				response_type: SELECTION_NOTIFY_EVENT,
+				sequence: 0,
+				time: request.time,
+				requestor: request.requestor,
+				selection: request.selection,
+				target: request.target,
+				property: if matches!(reply, SelectionReply::NotServed) { AtomEnum::NONE.into() } else { property }
+			}
+		)?.check()?;
+
+		self.connection.flush()?;
+
+		if let SelectionReply::Incr(data) = reply {
+			self.send_incr_chunks(request.requestor, property, request.target, data)?;
+		}
+
+		Ok(())
+	}
+
+	fn reply_targets(&self, requestor: Window, property: Atom) -> Result<(), X11ClipboardMonitorError> {
+		let targets = [self.atoms.targets, self.atoms.timestamp, self.atoms.utf8_string, self.atoms.string];
+
+		self.change_property32(requestor, property, AtomEnum::ATOM.into(), &targets)
+	}
+
+	fn reply_timestamp(&self, requestor: Window, property: Atom) -> Result<(), X11ClipboardMonitorError> {
+		self.change_property32(requestor, property, AtomEnum::INTEGER.into(), &[self.owned_since.get()])
+	}
+
+	fn reply_string(&self, requestor: Window, property: Atom, target: Atom) -> Result<SelectionReply, X11ClipboardMonitorError> {
+		let data = self.owned_selection.borrow().clone().unwrap_or_default();
+
+		if data.len() > self.max_chunk_size() {
+			self.announce_incr(requestor, property, data.len())?;
+			Ok(SelectionReply::Incr(data))
+		} else {
+			self.change_property8(requestor, property, target, &data)?;
+			Ok(SelectionReply::Served)
+		}
+	}
+
+	// ICCCM INCR: announce the total size via a format-32 INCR property; the requestor
+	// must see the SelectionNotify for this request before it starts asking for chunks,
+	// so the chunk loop itself runs separately, in send_incr_chunks, after that's sent
+	fn announce_incr(&self, requestor: Window, property: Atom, total_len: usize) -> Result<(), X11ClipboardMonitorError> {
+		self.connection.change_window_attributes(
+			requestor,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+		)?.check()?;
+
+		self.change_property32(requestor, property, self.atoms.incr, &[total_len as u32])?;
+
+		Ok(())
+	}
+
+	// appends successive chunks each time the requestor deletes the property to ask for
+	// more, finishing with a zero-length write; the deadline only bounds the gap between
+	// chunks, not the whole transfer, so a requestor that's still actively consuming never
+	// times out (mirrors receive_incr_chunks on the read side)
+	fn send_incr_chunks(&self, requestor: Window, property: Atom, target: Atom, data: Vec<u8>) -> Result<(), X11ClipboardMonitorError> {
+		let mut chunks = data.chunks(self.max_chunk_size());
+		let mut deadline = Instant::now() + INCR_TIMEOUT;
+
+		loop {
+			let event = match self.connection.poll_for_event()? {
+				Some(event) => event,
+				None => {
+					if Instant::now() >= deadline {
+						return Err(X11ClipboardMonitorError::IncrSendTimeout);
+					}
+
+					thread::sleep(Duration::from_millis(10));
+					continue
+				}
+			};
+
+			let Event::PropertyNotify(event) = event else { continue };
+
+			if event.window != requestor || event.atom != property || event.state != Property::DELETE {
+				continue;
+			}
+
+			let chunk = chunks.next().unwrap_or(&[]);
+			let done = chunk.is_empty();
+
+			self.change_property8(requestor, property, target, chunk)?;
+			self.connection.flush()?;
+
+			deadline = Instant::now() + INCR_TIMEOUT;
+
+			if done {
+				break;
+			}
+		}
+
+		self.connection.change_window_attributes(
+			requestor,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+		)?.check()?;
+
+		Ok(())
+	}
+
+	fn max_chunk_size(&self) -> usize {
+		self.connection.maximum_request_bytes().saturating_sub(64)
+	}
+
+	fn change_property8(&self, window: Window, property: Atom, type_: Atom, data: &[u8]) -> Result<(), X11ClipboardMonitorError> {
+		self.connection.change_property(PropMode::REPLACE, window, property, type_, 8, data.len() as u32, data)?.check()?;
+		Ok(())
+	}
+
+	fn change_property32(&self, window: Window, property: Atom, type_: Atom, values: &[u32]) -> Result<(), X11ClipboardMonitorError> {
+		let data: Vec<u8> = values.iter().flat_map(|value| value.to_ne_bytes()).collect();
+		self.connection.change_property(PropMode::REPLACE, window, property, type_, 32, values.len() as u32, &data)?.check()?;
+		Ok(())
+	}
+
+	// ICCCM: obtain a real server timestamp (rather than CurrentTime) to claim ownership
+	// with, by round-tripping a property change on our own window and reading its time
+	fn current_timestamp(&self) -> Result<Timestamp, X11ClipboardMonitorError> {
+		self.connection.change_window_attributes(
+			self.receiver_window,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+		)?.check()?;
+
+		self.change_property8(self.receiver_window, self.atoms.receiver_property, AtomEnum::STRING.into(), &[])?;
+		self.connection.flush()?;
+
+		let timestamp = loop {
+			if let Event::PropertyNotify(event) = self.connection.wait_for_event()? {
+				#[allow(clippy::collapsible_if)]
+				if event.window == self.receiver_window && event.atom == self.atoms.receiver_property {
+					break event.time;
+				}
+			}
+		};
+
+		self.connection.change_window_attributes(
+			self.receiver_window,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+		)?.check()?;
+
+		Ok(timestamp)
+	}
+
+	fn wait_for_selection_change(&self) -> Result<XfixesSelectionNotifyEvent, X11ClipboardMonitorError> {
+		loop {
+			if let Event::XfixesSelectionNotify(event) = self.connection.wait_for_event()? {
+				return Ok(event);
+			}
+		}
+	}
+
+	fn poll_for_selection_change(&self) -> Result<Option<XfixesSelectionNotifyEvent>, X11ClipboardMonitorError> {
+		loop {
+			match self.connection.poll_for_event()? {
+				Some(Event::XfixesSelectionNotify(event)) => return Ok(Some(event)),
+				Some(_) => continue,
+				None => return Ok(None),
+			};
+		}
+	}
 
-		self.connection.get_selection_owner(self.atoms.clipboard)?
+	// converts `selection` to `target` and fetches the resulting property, following the
+	// INCR protocol transparently when the owner announces one
+	fn convert_and_fetch(&self, selection: Atom, target: Atom, timestamp: Timestamp) -> Result<Vec<u8>, X11ClipboardMonitorError> {
+		let owner = self.connection.get_selection_owner(self.atoms.selection)?
 			.reply()
-			.map_err(|_| Box::new(X11ClipboardMonitorError::SelectionOrphaned))?;
+			.map_err(|_| X11ClipboardMonitorError::SelectionOrphaned)?
+			.owner;
+
+		if owner == x11rb::NONE {
+			return Err(X11ClipboardMonitorError::SelectionOrphaned);
+		}
 
 		self.connection.convert_selection(
 			self.receiver_window,
-			clipboard_changed_event.selection,
-			self.atoms.utf8_string,
+			selection,
+			target,
 			self.atoms.receiver_property,
-			clipboard_changed_event.timestamp
+			timestamp
 		)?.check()?;
 
 		self.connection.flush()?;
 
 		loop {
-			match self.connection.wait_for_event()? {
-				Event::SelectionNotify(event) => {
-					if event.property == AtomEnum::NONE.into() {
-						return Err(Box::new(X11ClipboardMonitorError::ConversionFailed));
-					}
+			let Event::SelectionNotify(event) = self.connection.wait_for_event()? else { continue };
 
-					break
-				},
-				_ => (),
-			};
+			if event.property == AtomEnum::NONE.into() {
+				return Err(X11ClipboardMonitorError::ConversionFailed);
+			}
+
+			break;
 		}
 
 		let conversion_property = self.connection.get_property(
@@ -123,9 +497,8 @@ impl X11ClipboardMonitor {
 			0
 		)?.reply()?;
 
-		// should be implemented if large clipboard data should also be retrievable
 		if conversion_property.type_ == self.atoms.incr {
-			return Err(Box::new(X11ClipboardMonitorError::IncrUnsupported));
+			return self.receive_incr();
 		}
 
 		let conversion_property_value = self.connection.get_property(
@@ -137,6 +510,174 @@ impl X11ClipboardMonitor {
 			conversion_property.bytes_after,
 		)?.reply()?.value;
 
-		Ok(str::from_utf8(&conversion_property_value)?.into())
+		Ok(conversion_property_value)
+	}
+
+	/// Requests the `TARGETS` target and returns the atoms the current selection owner
+	/// is able to convert the selection to.
+	pub fn available_targets(&self) -> Result<Vec<Atom>, X11ClipboardMonitorError> {
+		self.connection.convert_selection(
+			self.receiver_window,
+			self.atoms.selection,
+			self.atoms.targets,
+			self.atoms.receiver_property,
+			x11rb::CURRENT_TIME
+		)?.check()?;
+
+		self.connection.flush()?;
+
+		loop {
+			let Event::SelectionNotify(event) = self.connection.wait_for_event()? else { continue };
+
+			if event.property == AtomEnum::NONE.into() {
+				return Err(X11ClipboardMonitorError::ConversionFailed);
+			}
+
+			break;
+		}
+
+		let targets = self.connection.get_property(
+			true,
+			self.receiver_window,
+			self.atoms.receiver_property,
+			AtomEnum::ATOM,
+			0,
+			u32::MAX,
+		)?.reply()?;
+
+		Ok(targets.value32().map(Iterator::collect).unwrap_or_default())
+	}
+
+	// mirrors Chromium's text/plain[;charset=utf-8] <-> [UTF8_]STRING bridging so we can
+	// read from apps that only advertise MIME-typed targets
+	fn preferred_string_target(&self) -> Result<Atom, X11ClipboardMonitorError> {
+		let available = self.available_targets()?;
+		let preference = [self.atoms.utf8_string, self.atoms.string, self.atoms.text_plain_utf8, self.atoms.text_plain];
+
+		Ok(pick_preferred_target(&preference, &available))
+	}
+
+	// the owner couldn't fit the whole selection into a single property, so it announced
+	// an INCR transfer: we watch receiver_property for a series of appended chunks,
+	// terminated by an empty one, as described in the ICCCM section on INCR properties
+	fn receive_incr(&self) -> Result<Vec<u8>, X11ClipboardMonitorError> {
+		self.connection.change_window_attributes(
+			self.receiver_window,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+		)?.check()?;
+
+		self.connection.delete_property(self.receiver_window, self.atoms.receiver_property)?.check()?;
+		self.connection.flush()?;
+
+		let result = self.receive_incr_chunks();
+
+		self.connection.change_window_attributes(
+			self.receiver_window,
+			&ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT),
+		)?.check()?;
+		self.connection.flush()?;
+
+		result
+	}
+
+	fn receive_incr_chunks(&self) -> Result<Vec<u8>, X11ClipboardMonitorError> {
+		// the deadline only bounds the gap between chunks, not the whole transfer, so a
+		// large selection that's still actively streaming never times out
+		let mut deadline = Instant::now() + INCR_TIMEOUT;
+		let mut buffer = Vec::new();
+
+		loop {
+			let event = match self.connection.poll_for_event()? {
+				Some(event) => event,
+				None => {
+					if Instant::now() >= deadline {
+						return Err(X11ClipboardMonitorError::IncrTimeout);
+					}
+
+					thread::sleep(Duration::from_millis(10));
+					continue
+				}
+			};
+
+			let Event::PropertyNotify(event) = event else { continue };
+
+			if event.atom != self.atoms.receiver_property || event.state != Property::NEW_VALUE {
+				continue;
+			}
+
+			let chunk = self.connection.get_property(
+				true,
+				self.receiver_window,
+				self.atoms.receiver_property,
+				AtomEnum::ANY,
+				0,
+				u32::MAX,
+			)?.reply()?;
+
+			if chunk.value.is_empty() {
+				break
+			}
+
+			buffer.extend_from_slice(&chunk.value);
+			deadline = Instant::now() + INCR_TIMEOUT;
+		}
+
+		Ok(buffer)
+	}
+}
+
+impl ClipboardMonitor for X11ClipboardMonitor {
+	fn next_clipboard_string(&self) -> Result<String, ClipboardMonitorError> {
+		Ok(X11ClipboardMonitor::next_clipboard_string(self)?)
+	}
+
+	fn poll_clipboard_string(&self) -> Result<Option<String>, ClipboardMonitorError> {
+		Ok(X11ClipboardMonitor::poll_clipboard_string(self)?)
+	}
+
+	fn drain_clipboard_strings(&self) -> Result<Vec<String>, ClipboardMonitorError> {
+		Ok(X11ClipboardMonitor::drain_clipboard_strings(self)?)
+	}
+
+	fn set_clipboard_string(&self, data: String) -> Result<(), ClipboardMonitorError> {
+		Ok(X11ClipboardMonitor::set_clipboard_string(self, data)?)
+	}
+
+	fn file_descriptor(&self) -> RawFd {
+		X11ClipboardMonitor::file_descriptor(self)
+	}
+}
+
+// picks the most preferred atom that's actually available, falling back to the most
+// preferred one regardless (the owner will just fail the conversion) if nothing matches
+fn pick_preferred_target(preference: &[Atom], available: &[Atom]) -> Atom {
+	preference.iter().copied().find(|target| available.contains(target)).unwrap_or(preference[0])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn selection_kind_atom_names() {
+		assert_eq!(SelectionKind::Clipboard.atom_name(), b"CLIPBOARD");
+		assert_eq!(SelectionKind::Primary.atom_name(), b"PRIMARY");
+		assert_eq!(SelectionKind::Secondary.atom_name(), b"SECONDARY");
+	}
+
+	#[test]
+	fn preferred_target_picks_first_available_in_preference_order() {
+		let preference = [1, 2, 3, 4];
+
+		assert_eq!(pick_preferred_target(&preference, &[3, 4]), 3);
+		assert_eq!(pick_preferred_target(&preference, &[4, 2]), 2);
+	}
+
+	#[test]
+	fn preferred_target_falls_back_to_the_top_preference_when_nothing_matches() {
+		let preference = [1, 2, 3, 4];
+
+		assert_eq!(pick_preferred_target(&preference, &[]), 1);
+		assert_eq!(pick_preferred_target(&preference, &[99]), 1);
 	}
 }
\ No newline at end of file